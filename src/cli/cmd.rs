@@ -2,7 +2,7 @@ use crate::compiler::TargetValueRef;
 use std::{
     collections::BTreeMap,
     fs::File,
-    io::{self, Read},
+    io::{self, BufRead, BufReader, Read, Write},
     iter::IntoIterator,
     path::PathBuf,
 };
@@ -14,15 +14,24 @@ use crate::compiler::{
     CompilationResult, CompileConfig, Function, Program, Target, TypeState, VrlRuntime,
     compile_with_state,
 };
-use crate::diagnostic::Formatter;
+use crate::diagnostic::{DiagnosticList, Formatter};
 use crate::owned_metadata_path;
+use crate::path::{OwnedValuePath, parse_value_path};
 use crate::value::Secrets;
 use crate::value::Value;
 use clap::Parser;
+use encoding_rs::Encoding;
+use flate2::bufread::MultiGzDecoder;
 
 use super::Error;
 use super::repl;
 
+/// Stdlib functions that make network requests or DNS queries.
+const NETWORK_FUNCTIONS: &[&str] = &["dns_lookup", "http_request", "reverse_dns"];
+
+/// The magic bytes every gzip stream starts with.
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
 #[derive(Parser, Debug)]
 #[command(name = "VRL", about = "Infopercept Remap Language CLI")]
 pub struct Opts {
@@ -32,6 +41,7 @@ pub struct Opts {
     program: Option<String>,
 
     /// The file containing the event object(s) to handle. JSON events should be one per line..
+    /// Gzip-compressed input is decompressed transparently.
     #[arg(short, long = "input")]
     input_file: Option<PathBuf>,
 
@@ -48,6 +58,12 @@ pub struct Opts {
     #[arg(short = 'z', long)]
     timezone: Option<String>,
 
+    /// Read the timezone of each event from this field, for example `.tz`. Events where the field
+    /// is missing or does not name a valid timezone fall back to `--timezone`. Not supported in
+    /// the REPL.
+    #[arg(long = "tz-from-field")]
+    tz_from_field: Option<String>,
+
     /// Should we use the VM to evaluate the VRL
     #[arg(short, long = "runtime", default_value_t)]
     runtime: VrlRuntime,
@@ -55,6 +71,36 @@ pub struct Opts {
     // Should the CLI emit warnings
     #[arg(long = "print-warnings")]
     print_warnings: bool,
+
+    /// Prefix errors with the input file and line they occurred on, and include their underlying
+    /// causes and the offending input line.
+    #[arg(long = "verbose-errors")]
+    verbose_errors: bool,
+
+    /// Exit with an error if no input event was processed successfully, including when the input
+    /// is empty. Not supported in the REPL.
+    #[arg(long = "require-output")]
+    require_output: bool,
+
+    /// Disallow functions that access the network, such as `dns_lookup` and `http_request`.
+    /// Programs that call them fail to compile.
+    #[arg(long = "deny-network")]
+    deny_network: bool,
+
+    /// Treat compiler warnings as errors, refusing to run a program that emits any. Not
+    /// supported in the REPL.
+    #[arg(long)]
+    strict: bool,
+
+    /// Stop once this many events have been written. Events that fail at runtime do not count
+    /// towards the limit. Useful for sampling the head of a large input. Not supported in the REPL.
+    #[arg(long = "max-events")]
+    max_events: Option<usize>,
+
+    /// The character encoding of the input, for example `latin1` or `windows-1252`. Each line is
+    /// decoded to UTF-8 before it is parsed. Defaults to UTF-8.
+    #[arg(long = "input-encoding")]
+    input_encoding: Option<String>,
 }
 
 impl Opts {
@@ -67,6 +113,13 @@ impl Opts {
         }
     }
 
+    fn tz_field(&self) -> Result<Option<OwnedValuePath>, Error> {
+        self.tz_from_field
+            .as_deref()
+            .map(|path| parse_value_path(path).map_err(|err| Error::Parse(err.to_string())))
+            .transpose()
+    }
+
     fn read_program(&self) -> Result<String, Error> {
         match self.program.as_ref() {
             Some(source) => Ok(source.clone()),
@@ -77,19 +130,91 @@ impl Opts {
         }
     }
 
-    fn read_into_objects(&self) -> Result<Vec<Value>, Error> {
-        let input = match self.input_file.as_ref() {
-            Some(path) => read(File::open(path)?),
-            None => read(io::stdin()),
-        }?;
+    fn input_reader(&self) -> Result<Box<dyn BufRead>, Error> {
+        let reader: Box<dyn BufRead> = match self.input_file.as_ref() {
+            Some(path) => Box::new(BufReader::new(File::open(path)?)),
+            None => Box::new(BufReader::new(io::stdin())),
+        };
+
+        Ok(decompress(reader)?)
+    }
+
+    fn input_encoding(&self) -> Result<&'static Encoding, Error> {
+        let Some(label) = self.input_encoding.as_ref() else {
+            return Ok(encoding_rs::UTF_8);
+        };
+
+        match Encoding::for_label(label.as_bytes()) {
+            // Lines are split on `\n` bytes before decoding, which is only sound for encodings
+            // that leave ASCII untouched.
+            Some(encoding) if encoding.is_ascii_compatible() => Ok(encoding),
+            Some(encoding) => Err(Error::Parse(format!(
+                "unsupported input encoding: {}",
+                encoding.name()
+            ))),
+            None => Err(Error::Parse(format!("unknown input encoding: {label}"))),
+        }
+    }
+
+    fn read_into_objects(&self) -> Result<InputIter, Error> {
+        Ok(parse_objects(self.input_reader()?, self.input_encoding()?))
+    }
+
+    /// Describes `err` for the user. With `--verbose-errors` the message is prefixed with the
+    /// input location and followed by the chain of underlying causes and the raw input line, if
+    /// known.
+    fn describe_error(&self, line: usize, err: &Error, input: Option<&str>) -> String {
+        if !self.verbose_errors {
+            return err.to_string();
+        }
+
+        let name = self
+            .input_file
+            .as_ref()
+            .map_or_else(|| "stdin".into(), |path| path.to_string_lossy());
+
+        let causes = std::iter::successors(std::error::Error::source(err), |cause| cause.source())
+            .map(|cause| format!("\n  caused by: {cause}"))
+            .collect::<String>();
+
+        match input {
+            Some(input) => format!("{name}:{line}: {err}{causes}\n  input: {input}"),
+            None => format!("{name}:{line}: {err}{causes}"),
+        }
+    }
+
+    /// Renders compile diagnostics, or warnings rejected by `--strict`, naming the program file if
+    /// the program was read from one.
+    fn compile_error(&self, source: &str, diagnostics: DiagnosticList) -> Error {
+        let diagnostics = Formatter::new(source, diagnostics).colored().to_string();
+
+        match &self.program_file {
+            Some(path) => Error::Parse(format!(
+                "failed to compile {}:\n{diagnostics}",
+                path.display()
+            )),
+            None => Error::Parse(diagnostics),
+        }
+    }
+
+    /// Fails under `--strict` if compiling the program emitted any warnings.
+    fn check_warnings(&self, source: &str, warnings: &DiagnosticList) -> Result<(), Error> {
+        if self.strict && warnings.has_warnings() {
+            return Err(self.compile_error(source, warnings.clone()));
+        }
+
+        Ok(())
+    }
 
-        match input.as_str() {
-            "" => Ok(vec![Value::Object(BTreeMap::default())]),
-            _ => input
-                .lines()
-                .map(|line| Ok(serde_to_vrl(serde_json::from_str(line)?)))
-                .collect::<Result<Vec<Value>, Error>>(),
+    /// Fails under `--require-output` if no input event was processed successfully.
+    fn check_output(&self, succeeded: usize) -> Result<(), Error> {
+        if self.require_output && succeeded == 0 {
+            return Err(Error::Parse(
+                "no input events were processed successfully".to_owned(),
+            ));
         }
+
+        Ok(())
     }
 
     fn should_open_repl(&self) -> bool {
@@ -97,6 +222,21 @@ impl Opts {
     }
 }
 
+/// An input event, along with the raw line it was parsed from.
+struct Input {
+    /// The raw input line. This is `None` for the empty event used in place of empty input, and
+    /// for lines that could not be read at all.
+    line: Option<String>,
+    event: Result<Value, Error>,
+}
+
+/// A lazily parsed stream of input events.
+type InputIter = Box<dyn Iterator<Item = Input>>;
+
+/// A decoded input line. A line that fails to decode is returned with its error, along with the
+/// line decoded lossily, if it could be read, so that it can still be reported.
+type Line = Result<String, (Option<String>, io::Error)>;
+
 #[must_use]
 pub fn cmd(opts: &Opts, stdlib_functions: Vec<Box<dyn Function>>) -> exitcode::ExitCode {
     match run(opts, stdlib_functions) {
@@ -111,14 +251,34 @@ pub fn cmd(opts: &Opts, stdlib_functions: Vec<Box<dyn Function>>) -> exitcode::E
     }
 }
 
-fn run(opts: &Opts, stdlib_functions: Vec<Box<dyn Function>>) -> Result<(), Error> {
+fn run(opts: &Opts, mut stdlib_functions: Vec<Box<dyn Function>>) -> Result<(), Error> {
     let tz = opts.timezone()?;
+    let tz_field = opts.tz_field()?;
+    if opts.deny_network {
+        remove_network_functions(&mut stdlib_functions);
+    }
+
     // Run the REPL if no program or program file is specified
     if opts.should_open_repl() {
+        if opts.strict {
+            return Err(Error::ReplFlag("--strict"));
+        }
+        if opts.max_events.is_some() {
+            return Err(Error::ReplFlag("--max-events"));
+        }
+        if opts.require_output {
+            return Err(Error::ReplFlag("--require-output"));
+        }
+        if tz_field.is_some() {
+            return Err(Error::ReplFlag("--tz-from-field"));
+        }
+
         // If an input file is provided, use that for the REPL objects, otherwise provide a
         // generic default object.
         let repl_objects = if opts.input_file.is_some() {
             opts.read_into_objects()?
+                .map(|input| input.event)
+                .collect::<Result<Vec<_>, _>>()?
         } else {
             default_objects()
         };
@@ -138,15 +298,10 @@ fn run(opts: &Opts, stdlib_functions: Vec<Box<dyn Function>>) -> Result<(), Erro
             program,
             warnings,
             config: _,
-        } = compile_with_state(
-            &source,
-            &crate::stdlib::all(),
-            &state,
-            CompileConfig::default(),
-        )
-        .map_err(|diagnostics| {
-            Error::Parse(Formatter::new(&source, diagnostics).colored().to_string())
-        })?;
+        } = compile_with_state(&source, &stdlib_functions, &state, CompileConfig::default())
+            .map_err(|diagnostics| opts.compile_error(&source, diagnostics))?;
+
+        opts.check_warnings(&source, &warnings)?;
 
         #[allow(clippy::print_stderr)]
         if opts.print_warnings {
@@ -154,35 +309,83 @@ fn run(opts: &Opts, stdlib_functions: Vec<Box<dyn Function>>) -> Result<(), Erro
             eprintln!("{warnings}");
         }
 
-        for mut object in objects {
-            let mut metadata = Value::Object(BTreeMap::new());
-            let mut secrets = Secrets::new();
-            let mut target = TargetValueRef {
-                value: &mut object,
-                metadata: &mut metadata,
-                secrets: &mut secrets,
-            };
-            let state = RuntimeState::default();
-            let runtime = Runtime::new(state);
-
-            let result = execute(&mut target, &program, tz, runtime, opts.runtime).map(|v| {
-                if opts.print_object {
-                    object.to_string()
-                } else {
-                    v.to_string()
-                }
-            });
+        let succeeded = process(
+            opts,
+            objects,
+            &program,
+            tz,
+            tz_field.as_ref(),
+            &mut io::stdout().lock(),
+            &mut io::stderr().lock(),
+        )?;
 
-            #[allow(clippy::print_stdout)]
-            #[allow(clippy::print_stderr)]
-            match result {
-                Ok(ok) => println!("{ok}"),
-                Err(err) => eprintln!("{err}"),
-            }
+        opts.check_output(succeeded)
+    }
+}
+
+/// Runs `program` over each input event, writing results to `out` and runtime errors to `err`.
+/// Each event is resolved in the timezone named by its `tz_field`, falling back to `tz`.
+///
+/// Returns the number of input events processed successfully. The empty event used in place of
+/// empty input is not counted, so that `--require-output` also catches empty input.
+fn process(
+    opts: &Opts,
+    inputs: InputIter,
+    program: &Program,
+    tz: TimeZone,
+    tz_field: Option<&OwnedValuePath>,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<usize, Error> {
+    // A single runtime is reused across events, cleared after each one so that no variables
+    // leak from one event into the next.
+    let mut runtime = Runtime::new(RuntimeState::default());
+    let mut succeeded = 0_usize;
+    let mut written = 0_usize;
+
+    for (number, Input { line, event }) in (1..).zip(inputs) {
+        if opts.max_events.is_some_and(|max| written >= max) {
+            break;
         }
 
-        Ok(())
+        let mut object = event
+            .map_err(|err| Error::Parse(opts.describe_error(number, &err, line.as_deref())))?;
+        let event_tz = event_timezone(&object, tz_field, tz);
+        let mut metadata = Value::Object(BTreeMap::new());
+        let mut secrets = Secrets::new();
+        let mut target = TargetValueRef {
+            value: &mut object,
+            metadata: &mut metadata,
+            secrets: &mut secrets,
+        };
+        let result = execute(&mut target, program, event_tz, &mut runtime, opts.runtime);
+        runtime.clear();
+
+        let result = result.map(|v| {
+            if opts.print_object {
+                object.to_string()
+            } else {
+                v.to_string()
+            }
+        });
+
+        match result {
+            Ok(ok) => {
+                if line.is_some() {
+                    succeeded += 1;
+                }
+                written += 1;
+                writeln!(out, "{ok}")?;
+            }
+            Err(error) => writeln!(
+                err,
+                "{}",
+                opts.describe_error(number, &error, line.as_deref())
+            )?,
+        }
     }
+
+    Ok(succeeded)
 }
 
 #[allow(clippy::unnecessary_wraps)]
@@ -210,7 +413,7 @@ fn execute(
     object: &mut impl Target,
     program: &Program,
     timezone: TimeZone,
-    mut runtime: Runtime,
+    runtime: &mut Runtime,
     vrl_runtime: VrlRuntime,
 ) -> Result<Value, Error> {
     match vrl_runtime {
@@ -220,6 +423,20 @@ fn execute(
     }
 }
 
+/// Removes the functions that access the network from `functions`.
+fn remove_network_functions(functions: &mut Vec<Box<dyn Function>>) {
+    functions.retain(|function| !NETWORK_FUNCTIONS.contains(&function.identifier()));
+}
+
+/// Returns the timezone named by the field at `path` in `object`, or `default` if there is no such
+/// field or it does not name a valid timezone.
+fn event_timezone(object: &Value, path: Option<&OwnedValuePath>, default: TimeZone) -> TimeZone {
+    path.and_then(|path| object.get(path))
+        .and_then(Value::as_str)
+        .and_then(|tz| TimeZone::parse(&tz))
+        .unwrap_or(default)
+}
+
 fn serde_to_vrl(value: serde_json::Value) -> Value {
     use serde_json::Value as JsonValue;
 
@@ -238,6 +455,98 @@ fn serde_to_vrl(value: serde_json::Value) -> Value {
     }
 }
 
+/// Parses one JSON event per line, reading lazily so that memory use is bounded by a single line
+/// rather than the whole input. Empty input yields a single empty object.
+fn parse_objects(reader: impl BufRead + 'static, encoding: &'static Encoding) -> InputIter {
+    let mut lines = decode_lines(reader, encoding).peekable();
+
+    if lines.peek().is_none() {
+        return Box::new(default_objects().into_iter().map(|event| Input {
+            line: None,
+            event: Ok(event),
+        }));
+    }
+
+    Box::new(lines.map(|line| {
+        match line {
+            Ok(line) => Input {
+                event: serde_json::from_str(&line)
+                    .map(serde_to_vrl)
+                    .map_err(Into::into),
+                line: Some(line),
+            },
+            Err((line, err)) => Input {
+                line,
+                event: Err(err.into()),
+            },
+        }
+    }))
+}
+
+/// Wraps `reader` in a streaming gzip decoder if the input starts with the gzip magic bytes, so
+/// that rotated logs such as `auth.log.1.gz` can be read directly. Other input is returned as is.
+///
+/// A single read may return fewer bytes than the magic number, for example from a pipe, so this
+/// keeps reading until enough bytes are available or the input ends.
+fn decompress(mut reader: Box<dyn BufRead>) -> io::Result<Box<dyn BufRead>> {
+    let mut magic = [0; GZIP_MAGIC.len()];
+    let mut len = 0;
+
+    while len < magic.len() {
+        match reader.read(&mut magic[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    let reader = io::Cursor::new(magic[..len].to_vec()).chain(reader);
+    if magic[..len] == *GZIP_MAGIC {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Splits the input into lines, decoding each one from `encoding` to UTF-8. Like
+/// [`BufRead::lines`], the trailing `\n` or `\r\n` is stripped and malformed input is an error.
+fn decode_lines(
+    mut reader: impl BufRead,
+    encoding: &'static Encoding,
+) -> impl Iterator<Item = Line> {
+    let mut buf = Vec::new();
+
+    std::iter::from_fn(move || {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => None,
+            Ok(_) => {
+                if buf.ends_with(b"\n") {
+                    buf.pop();
+                    if buf.ends_with(b"\r") {
+                        buf.pop();
+                    }
+                }
+
+                Some(
+                    match encoding.decode_without_bom_handling_and_without_replacement(&buf) {
+                        Some(line) => Ok(line.into_owned()),
+                        None => Err((
+                            Some(encoding.decode_without_bom_handling(&buf).0.into_owned()),
+                            io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("input line is not valid {}", encoding.name()),
+                            ),
+                        )),
+                    },
+                )
+            }
+            Err(err) => Some(Err((None, err))),
+        }
+    })
+}
+
 fn read<R: Read>(mut reader: R) -> Result<String, Error> {
     let mut buffer = String::new();
     reader.read_to_string(&mut buffer)?;
@@ -248,3 +557,483 @@ fn read<R: Read>(mut reader: R) -> Result<String, Error> {
 fn default_objects() -> Vec<Value> {
     vec![Value::Object(BTreeMap::new())]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::TargetValue;
+
+    fn events(inputs: InputIter) -> Result<Vec<Value>, Error> {
+        inputs.map(|input| input.event).collect()
+    }
+
+    fn compile_with(
+        source: &str,
+        functions: &[Box<dyn Function>],
+    ) -> Result<CompilationResult, DiagnosticList> {
+        compile_with_state(
+            source,
+            functions,
+            &TypeState::default(),
+            CompileConfig::default(),
+        )
+    }
+
+    fn compile(source: &str) -> Program {
+        compile_with(source, &crate::stdlib::all()).unwrap().program
+    }
+
+    /// Runs `source` over `input` with the given CLI arguments, returning the number of events
+    /// processed successfully and the lines written to stdout.
+    fn process_input(args: &[&str], source: &str, input: &'static str) -> (usize, Vec<String>) {
+        let opts = Opts::parse_from(["vrl"].iter().chain(args).chain(&[source]));
+        let inputs = parse_objects(input.as_bytes(), encoding_rs::UTF_8);
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+
+        let succeeded = process(
+            &opts,
+            inputs,
+            &compile(source),
+            TimeZone::default(),
+            None,
+            &mut out,
+            &mut err,
+        )
+        .unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        (succeeded, out.lines().map(str::to_owned).collect())
+    }
+
+    #[test]
+    fn parse_objects_empty_input() {
+        let objects = events(parse_objects(&b""[..], encoding_rs::UTF_8)).unwrap();
+
+        assert_eq!(objects, default_objects());
+    }
+
+    #[test]
+    fn parse_objects_streams_lines() {
+        let input = (0..10_000)
+            .map(|i| format!(r#"{{"n":{i}}}"#))
+            .collect::<Vec<_>>()
+            .join("\n");
+        // A tiny buffer splits lines across reads.
+        let reader = BufReader::with_capacity(16, io::Cursor::new(input.into_bytes()));
+
+        let mut count = 0;
+        for (i, object) in (0_i64..).zip(parse_objects(reader, encoding_rs::UTF_8)) {
+            let expected: Value = BTreeMap::from([("n".into(), Value::from(i))]).into();
+            assert_eq!(object.event.unwrap(), expected);
+            count += 1;
+        }
+
+        assert_eq!(count, 10_000);
+    }
+
+    #[test]
+    fn parse_objects_reads_lazily() {
+        /// Returns the first line, then panics if read any further.
+        struct FirstLine(Option<&'static [u8]>);
+
+        impl Read for FirstLine {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let line = self.0.take().expect("input read past the first line");
+                buf[..line.len()].copy_from_slice(line);
+                Ok(line.len())
+            }
+        }
+
+        let reader = BufReader::new(FirstLine(Some(b"{\"n\":0}\n")));
+        let mut objects = parse_objects(reader, encoding_rs::UTF_8);
+
+        let expected: Value = BTreeMap::from([("n".into(), Value::from(0))]).into();
+        assert_eq!(objects.next().unwrap().event.unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_objects_invalid_line() {
+        let mut objects = parse_objects(&b"{}\nnot json\n"[..], encoding_rs::UTF_8);
+
+        assert!(objects.next().unwrap().event.is_ok());
+
+        let input = objects.next().unwrap();
+        assert!(matches!(input.event, Err(Error::Json(_))));
+        assert_eq!(input.line.as_deref(), Some("not json"));
+    }
+
+    #[test]
+    fn parse_objects_latin1() {
+        let encoding = Encoding::for_label(b"latin1").unwrap();
+        let mut objects = parse_objects(&b"{\"message\":\"caf\xe9\"}\r\n"[..], encoding);
+
+        let expected: Value = BTreeMap::from([("message".into(), Value::from("café"))]).into();
+        assert_eq!(objects.next().unwrap().event.unwrap(), expected);
+        assert!(objects.next().is_none());
+    }
+
+    #[test]
+    fn parse_objects_invalid_utf8() {
+        let mut objects = parse_objects(&b"{\"message\":\"caf\xe9\"}"[..], encoding_rs::UTF_8);
+
+        let input = objects.next().unwrap();
+        assert!(matches!(input.event, Err(Error::Io(_))));
+        assert_eq!(input.line.as_deref(), Some("{\"message\":\"caf\u{fffd}\"}"));
+    }
+
+    #[test]
+    fn describe_error_verbose() {
+        let err = Error::Parse("function call error".to_owned());
+        let line = r#"{"message":"boom"}"#;
+
+        let opts = Opts::parse_from(["vrl", "-i", "events.json", "."]);
+        assert_eq!(
+            opts.describe_error(3, &err, Some(line)),
+            "function call error"
+        );
+
+        let opts = Opts::parse_from(["vrl", "--verbose-errors", "-i", "events.json", "."]);
+        assert_eq!(
+            opts.describe_error(3, &err, Some(line)),
+            "events.json:3: function call error\n  input: {\"message\":\"boom\"}"
+        );
+    }
+
+    #[test]
+    fn describe_error_verbose_malformed_line() {
+        let mut inputs = parse_objects(&b"{}\n{\"message\": \"boom\"\n"[..], encoding_rs::UTF_8);
+        assert!(inputs.next().unwrap().event.is_ok());
+
+        let Input { line, event } = inputs.next().unwrap();
+        let err = event.unwrap_err();
+
+        let opts = Opts::parse_from(["vrl", "-i", "events.json", "."]);
+        assert_eq!(
+            opts.describe_error(2, &err, line.as_deref()),
+            err.to_string()
+        );
+
+        let opts = Opts::parse_from(["vrl", "--verbose-errors", "-i", "events.json", "."]);
+        let cause = std::error::Error::source(&err).unwrap();
+        assert_eq!(
+            opts.describe_error(2, &err, line.as_deref()),
+            format!("events.json:2: {err}\n  caused by: {cause}\n  input: {{\"message\": \"boom\"")
+        );
+    }
+
+    #[test]
+    fn describe_error_verbose_cause_chain() {
+        #[derive(Debug, thiserror::Error)]
+        #[error("invalid gzip header")]
+        struct Header;
+
+        #[derive(Debug, thiserror::Error)]
+        #[error("corrupt input")]
+        struct Corrupt(#[source] Header);
+
+        let err = Error::from(io::Error::other(Corrupt(Header)));
+
+        let opts = Opts::parse_from(["vrl", "-i", "events.json", "."]);
+        assert_eq!(
+            opts.describe_error(1, &err, None),
+            "io error: corrupt input"
+        );
+
+        let opts = Opts::parse_from(["vrl", "--verbose-errors", "-i", "events.json", "."]);
+        assert_eq!(
+            opts.describe_error(1, &err, None),
+            "events.json:1: io error: corrupt input\n  caused by: corrupt input\n  caused by: \
+             invalid gzip header"
+        );
+    }
+
+    #[test]
+    fn compile_error_names_program_file() {
+        let source = ".a = ";
+        let diagnostics = || compile_with(source, &crate::stdlib::all()).err().unwrap();
+
+        let opts = Opts::parse_from(["vrl", "-p", "scripts/x.vrl"]);
+        let err = opts.compile_error(source, diagnostics()).to_string();
+        assert!(err.starts_with("failed to compile scripts/x.vrl:\n"));
+
+        let opts = Opts::parse_from(["vrl", source]);
+        let err = opts.compile_error(source, diagnostics()).to_string();
+        assert!(!err.contains("failed to compile"));
+    }
+
+    #[test]
+    fn strict_warnings() {
+        let source = "\"foo\"\n.a = 1";
+        let warnings = compile_with(source, &crate::stdlib::all())
+            .unwrap()
+            .warnings;
+        assert!(warnings.has_warnings());
+
+        let opts = Opts::parse_from(["vrl", source]);
+        assert!(opts.check_warnings(source, &warnings).is_ok());
+
+        let opts = Opts::parse_from(["vrl", "--strict", source]);
+        assert!(matches!(
+            opts.check_warnings(source, &warnings),
+            Err(Error::Parse(_))
+        ));
+
+        let opts = Opts::parse_from(["vrl", "--strict", "-p", "scripts/x.vrl"]);
+        let err = opts
+            .check_warnings(source, &warnings)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("failed to compile scripts/x.vrl:\n"));
+    }
+
+    #[test]
+    fn strict_repl() {
+        let opts = Opts::parse_from(["vrl", "--strict"]);
+        assert!(matches!(
+            run(&opts, crate::stdlib::all()),
+            Err(Error::ReplFlag("--strict"))
+        ));
+    }
+
+    #[test]
+    fn max_events() {
+        let input = "{\"n\": 1}\n{\"n\": 2}\n{\"n\": 3}\n{\"n\": 4}\n{\"n\": 5}\n";
+
+        let (succeeded, out) = process_input(&["--max-events", "2"], ".n", input);
+        assert_eq!(succeeded, 2);
+        assert_eq!(out, ["1", "2"]);
+
+        let (succeeded, out) = process_input(&["--max-events", "0"], ".n", input);
+        assert_eq!(succeeded, 0);
+        assert!(out.is_empty());
+
+        let (succeeded, out) = process_input(&[], ".n", input);
+        assert_eq!(succeeded, 5);
+        assert_eq!(out.len(), 5);
+    }
+
+    #[test]
+    fn max_events_skips_failed_events() {
+        let input = "{\"n\": \"1\"}\n{\"n\": \"a\"}\n{\"n\": \"3\"}\n{\"n\": \"4\"}\n";
+
+        let (succeeded, out) = process_input(&["--max-events", "2"], "to_int!(.n)", input);
+        assert_eq!(succeeded, 2);
+        assert_eq!(out, ["1", "3"]);
+    }
+
+    #[test]
+    fn max_events_repl() {
+        let opts = Opts::parse_from(["vrl", "--max-events", "2"]);
+        assert!(matches!(
+            run(&opts, crate::stdlib::all()),
+            Err(Error::ReplFlag("--max-events"))
+        ));
+    }
+
+    #[test]
+    fn require_output_repl() {
+        let opts = Opts::parse_from(["vrl", "--require-output"]);
+        assert!(matches!(
+            run(&opts, crate::stdlib::all()),
+            Err(Error::ReplFlag("--require-output"))
+        ));
+    }
+
+    #[test]
+    fn require_output_failing_program() {
+        let (succeeded, out) = process_input(&[], ".n = to_int!(.n)", "{\"n\": \"a\"}\n");
+        assert_eq!((succeeded, out.len()), (0, 0));
+
+        assert!(
+            Opts::parse_from(["vrl", "."])
+                .check_output(succeeded)
+                .is_ok()
+        );
+        assert!(
+            Opts::parse_from(["vrl", "--require-output", "."])
+                .check_output(succeeded)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn require_output_passing_program() {
+        let (succeeded, out) = process_input(&[], ".n = to_int!(.n)", "{\"n\": \"1\"}\n");
+        assert_eq!((succeeded, out.len()), (1, 1));
+
+        assert!(
+            Opts::parse_from(["vrl", "--require-output", "."])
+                .check_output(succeeded)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn require_output_empty_input() {
+        let (succeeded, out) = process_input(&[], ".", "");
+        assert_eq!(succeeded, 0);
+        assert_eq!(out, ["{}"]);
+
+        assert!(
+            Opts::parse_from(["vrl", "--require-output", "."])
+                .check_output(succeeded)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn execute_reuses_runtime() {
+        let program = compile("x = to_int!(.n) * 2; .doubled = x; x");
+
+        let target = |n: i64| TargetValue {
+            value: BTreeMap::from([("n".into(), Value::from(n))]).into(),
+            metadata: Value::Object(BTreeMap::new()),
+            secrets: Secrets::new(),
+        };
+
+        let mut runtime = Runtime::new(RuntimeState::default());
+        for n in 0..100 {
+            let mut reused = target(n);
+            let mut fresh = target(n);
+
+            let result = execute(
+                &mut reused,
+                &program,
+                TimeZone::default(),
+                &mut runtime,
+                VrlRuntime::Ast,
+            );
+            runtime.clear();
+            let expected = execute(
+                &mut fresh,
+                &program,
+                TimeZone::default(),
+                &mut Runtime::new(RuntimeState::default()),
+                VrlRuntime::Ast,
+            );
+
+            assert_eq!(result.unwrap(), expected.unwrap());
+            assert_eq!(reused.value, fresh.value);
+            assert!(runtime.is_empty());
+        }
+    }
+
+    #[test]
+    fn event_timezone_from_field() {
+        let program = compile(r#"to_unix_timestamp(parse_timestamp!(.ts, "%Y-%m-%d %H:%M:%S"))"#);
+        let path = parse_value_path(".tz").unwrap();
+        let default = TimeZone::parse("UTC").unwrap();
+
+        let resolve = |tz: &str| {
+            let mut target = TargetValue {
+                value: BTreeMap::from([
+                    ("ts".into(), Value::from("2024-01-01 00:00:00")),
+                    ("tz".into(), Value::from(tz)),
+                ])
+                .into(),
+                metadata: Value::Object(BTreeMap::new()),
+                secrets: Secrets::new(),
+            };
+            let timezone = event_timezone(&target.value, Some(&path), default);
+
+            execute(
+                &mut target,
+                &program,
+                timezone,
+                &mut Runtime::new(RuntimeState::default()),
+                VrlRuntime::Ast,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(resolve("UTC"), Value::from(1_704_067_200));
+        assert_eq!(resolve("Asia/Kolkata"), Value::from(1_704_067_200 - 19_800));
+        assert_eq!(resolve("Not/AZone"), Value::from(1_704_067_200));
+    }
+
+    #[test]
+    fn tz_from_field_checked_first() {
+        // The path is rejected before the (missing) input file is opened.
+        let opts = Opts::parse_from([
+            "vrl",
+            "--tz-from-field",
+            "..",
+            "-i",
+            "/nonexistent/events.json",
+            ".",
+        ]);
+        assert!(matches!(
+            run(&opts, crate::stdlib::all()),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn tz_from_field_repl() {
+        let opts = Opts::parse_from(["vrl", "--tz-from-field", ".tz"]);
+        assert!(matches!(
+            run(&opts, crate::stdlib::all()),
+            Err(Error::ReplFlag("--tz-from-field"))
+        ));
+    }
+
+    #[test]
+    fn deny_network_functions() {
+        let source = r#"dns_lookup!("example.com")"#;
+
+        let mut functions = crate::stdlib::all();
+        assert!(compile_with(source, &functions).is_ok());
+
+        remove_network_functions(&mut functions);
+        assert!(compile_with(source, &functions).is_err());
+        assert!(
+            functions
+                .iter()
+                .all(|function| !NETWORK_FUNCTIONS.contains(&function.identifier()))
+        );
+    }
+
+    #[test]
+    fn decompress_gzip_input() {
+        use flate2::{Compression, write::GzEncoder};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder
+            .write_all(b"{\"n\":1}\n{\"n\":2}\n{\"n\":3}\n")
+            .unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let expected: Value = BTreeMap::from([("n".into(), Value::from(3))]).into();
+
+        let reader = decompress(Box::new(io::Cursor::new(compressed.clone()))).unwrap();
+        let objects = events(parse_objects(reader, encoding_rs::UTF_8)).unwrap();
+        assert_eq!(objects.len(), 3);
+        assert_eq!(objects[2], expected);
+
+        // Readers that buffer or return a single byte at a time, like a slow pipe.
+        let buffered = BufReader::with_capacity(1, io::Cursor::new(compressed.clone()));
+        let split = io::Cursor::new(compressed[..1].to_vec())
+            .chain(io::Cursor::new(compressed[1..].to_vec()));
+        let readers: [Box<dyn BufRead>; 2] = [Box::new(buffered), Box::new(split)];
+
+        for reader in readers {
+            let reader = decompress(reader).unwrap();
+            let objects = events(parse_objects(reader, encoding_rs::UTF_8)).unwrap();
+            assert_eq!(objects.len(), 3);
+            assert_eq!(objects[2], expected);
+        }
+    }
+
+    #[test]
+    fn decompress_plain_input() {
+        let reader = decompress(Box::new(&b"{}\n"[..])).unwrap();
+        let objects = events(parse_objects(reader, encoding_rs::UTF_8)).unwrap();
+        assert_eq!(objects, default_objects());
+
+        let reader = decompress(Box::new(&b"{"[..])).unwrap();
+        let mut lines = decode_lines(reader, encoding_rs::UTF_8);
+        assert_eq!(lines.next().unwrap().unwrap(), "{");
+        assert!(lines.next().is_none());
+    }
+}
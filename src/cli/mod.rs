@@ -23,6 +23,9 @@ pub enum Error {
     #[error("repl feature disabled, program input required")]
     ReplFeature,
 
+    #[error("{} cannot be used in the repl, program input required", .0)]
+    ReplFlag(&'static str),
+
     #[error("error setting up readline: {}", .0)]
     Readline(#[from] rustyline::error::ReadlineError),
 }